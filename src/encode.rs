@@ -0,0 +1,202 @@
+//! Serializing a [`NamedTag`] back to NBT's binary format, the inverse of [`decode`] /
+//! [`decode_auto`].
+//!
+//! [`NamedTag`]: ../struct.NamedTag.html
+//! [`decode`]: ../fn.decode.html
+//! [`decode_auto`]: ../fn.decode_auto.html
+
+use std::io::Write;
+
+use libflate::gzip;
+use libflate::zlib;
+
+use mutf8::string_to_mutf8;
+use stream::TagId;
+use {NamedTag, UnnamedTag};
+
+/// The compression to apply when encoding, mirroring the formats [`decode_auto`] can detect.
+///
+/// [`decode_auto`]: ../fn.decode_auto.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression; the raw binary format.
+    None,
+    /// Gzip, as used by the spec for e.g. player data and level files.
+    Gzip,
+    /// Zlib, as used by region/chunk data.
+    Zlib,
+}
+
+/// Encode `tag` to `out` in NBT's binary format, compressed as specified by `compression`.
+///
+/// This is a genuine inverse of [`decode`]/[`decode_auto`]: parsing the result back with the
+/// matching compression yields an equal `NamedTag`.
+///
+/// [`decode`]: ../fn.decode.html
+/// [`decode_auto`]: ../fn.decode_auto.html
+pub fn encode<W: Write>(
+    tag: &NamedTag,
+    out: W,
+    compression: Compression,
+) -> Result<(), ::failure::Error> {
+    match compression {
+        Compression::None => {
+            let mut out = out;
+            write_named_tag(tag, &mut out)
+        }
+        Compression::Gzip => {
+            let mut encoder = gzip::Encoder::new(out)?;
+            write_named_tag(tag, &mut encoder)?;
+            encoder.finish().into_result()?;
+            Ok(())
+        }
+        Compression::Zlib => {
+            let mut encoder = zlib::Encoder::new(out)?;
+            write_named_tag(tag, &mut encoder)?;
+            encoder.finish().into_result()?;
+            Ok(())
+        }
+    }
+}
+
+fn write_named_tag<W: Write>(tag: &NamedTag, out: &mut W) -> Result<(), ::failure::Error> {
+    write_u8(out, tag_id(&tag.content) as u8)?;
+    write_name(out, &tag.name)?;
+    write_payload(&tag.content, out)
+}
+
+fn write_payload<W: Write>(content: &UnnamedTag, out: &mut W) -> Result<(), ::failure::Error> {
+    match content {
+        UnnamedTag::End => {}
+        UnnamedTag::Byte(n) => write_i8(out, *n)?,
+        UnnamedTag::Short(n) => write_i16(out, *n)?,
+        UnnamedTag::Int(n) => write_i32(out, *n)?,
+        UnnamedTag::Long(n) => write_i64(out, *n)?,
+        UnnamedTag::Float(n) => write_f32(out, *n)?,
+        UnnamedTag::Double(n) => write_f64(out, *n)?,
+        UnnamedTag::ByteArray(items) => {
+            write_i32(out, items.len() as i32)?;
+            for &n in items {
+                write_i8(out, n)?;
+            }
+        }
+        UnnamedTag::String(s) => write_name(out, s)?,
+        UnnamedTag::List(items) => {
+            let element_id = items.first().map(tag_id).unwrap_or(TagId::End);
+            write_u8(out, element_id as u8)?;
+            write_i32(out, items.len() as i32)?;
+            for item in items {
+                write_payload(item, out)?;
+            }
+        }
+        UnnamedTag::Compound(members) => {
+            for member in members {
+                write_named_tag(member, out)?;
+            }
+            write_u8(out, TagId::End as u8)?;
+        }
+        UnnamedTag::IntArray(items) => {
+            write_i32(out, items.len() as i32)?;
+            for &n in items {
+                write_i32(out, n)?;
+            }
+        }
+        UnnamedTag::LongArray(items) => {
+            write_i32(out, items.len() as i32)?;
+            for &n in items {
+                write_i64(out, n)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The tag id a given payload is written (and read back) under.
+fn tag_id(content: &UnnamedTag) -> TagId {
+    match content {
+        UnnamedTag::End => TagId::End,
+        UnnamedTag::Byte(_) => TagId::Byte,
+        UnnamedTag::Short(_) => TagId::Short,
+        UnnamedTag::Int(_) => TagId::Int,
+        UnnamedTag::Long(_) => TagId::Long,
+        UnnamedTag::Float(_) => TagId::Float,
+        UnnamedTag::Double(_) => TagId::Double,
+        UnnamedTag::ByteArray(_) => TagId::ByteArray,
+        UnnamedTag::String(_) => TagId::String,
+        UnnamedTag::List(_) => TagId::List,
+        UnnamedTag::Compound(_) => TagId::Compound,
+        UnnamedTag::IntArray(_) => TagId::IntArray,
+        UnnamedTag::LongArray(_) => TagId::LongArray,
+    }
+}
+
+fn write_u8<W: Write>(out: &mut W, n: u8) -> Result<(), ::failure::Error> {
+    Ok(out.write_all(&[n])?)
+}
+
+fn write_i8<W: Write>(out: &mut W, n: i8) -> Result<(), ::failure::Error> {
+    write_u8(out, n as u8)
+}
+
+fn write_u16<W: Write>(out: &mut W, n: u16) -> Result<(), ::failure::Error> {
+    Ok(out.write_all(&n.to_be_bytes())?)
+}
+
+fn write_i16<W: Write>(out: &mut W, n: i16) -> Result<(), ::failure::Error> {
+    Ok(out.write_all(&n.to_be_bytes())?)
+}
+
+fn write_i32<W: Write>(out: &mut W, n: i32) -> Result<(), ::failure::Error> {
+    Ok(out.write_all(&n.to_be_bytes())?)
+}
+
+fn write_i64<W: Write>(out: &mut W, n: i64) -> Result<(), ::failure::Error> {
+    Ok(out.write_all(&n.to_be_bytes())?)
+}
+
+fn write_f32<W: Write>(out: &mut W, n: f32) -> Result<(), ::failure::Error> {
+    write_i32(out, n.to_bits() as i32)
+}
+
+fn write_f64<W: Write>(out: &mut W, n: f64) -> Result<(), ::failure::Error> {
+    write_i64(out, n.to_bits() as i64)
+}
+
+fn write_name<W: Write>(out: &mut W, s: &str) -> Result<(), ::failure::Error> {
+    let bytes = string_to_mutf8(s);
+    write_u16(out, bytes.len() as u16)?;
+    Ok(out.write_all(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, Compression};
+    use {decode_uncompressed, NamedTag, UnnamedTag};
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let tag = NamedTag {
+            name: "root".to_owned(),
+            content: UnnamedTag::Compound(vec![
+                NamedTag {
+                    name: "greeting".to_owned(),
+                    content: UnnamedTag::String("hello".to_owned()),
+                },
+                NamedTag {
+                    name: "answer".to_owned(),
+                    content: UnnamedTag::Int(42),
+                },
+                NamedTag {
+                    name: "coords".to_owned(),
+                    content: UnnamedTag::LongArray(vec![1, 2, 3]),
+                },
+            ]),
+        };
+
+        let mut bytes = Vec::new();
+        encode(&tag, &mut bytes, Compression::None).unwrap();
+
+        let decoded = decode_uncompressed(&bytes[..]).unwrap();
+        assert_eq!(decoded, tag);
+    }
+}
@@ -3,7 +3,11 @@ extern crate combine;
 
 extern crate libflate;
 
+#[macro_use]
 extern crate failure;
+extern crate failure_derive;
+
+extern crate indexmap;
 
 use combine::parser::byte::{
     byte,
@@ -13,7 +17,18 @@ use combine::stream::{buffered::BufferedStream, state::State, ReadStream};
 use combine::{any, count, many, unexpected};
 use combine::{ParseError, Parser, Stream};
 
-use std::{io::Read, mem};
+use indexmap::IndexMap;
+
+use std::io::{self, Read};
+use std::mem;
+
+mod mutf8;
+pub mod stream;
+mod encode;
+
+use mutf8::mutf8_to_string;
+
+pub use encode::{encode, Compression};
 
 /// An unnamed tag.
 #[derive(Clone, Debug, PartialEq)]
@@ -52,6 +67,12 @@ pub enum UnnamedTag {
     /// The `TAG_Compound` tag. This contains named tags, but the `TAG_End` tag which is always
     /// present at the end is removed for ease of use.
     Compound(Vec<NamedTag>),
+
+    /// The `TAG_Int_Array` tag.
+    IntArray(Vec<i32>),
+
+    /// The `TAG_Long_Array` tag.
+    LongArray(Vec<i64>),
 }
 
 /// A named tag. Contains only the name on its own, and the actual tag's contents are accessible
@@ -64,6 +85,40 @@ pub struct NamedTag {
     pub content: UnnamedTag,
 }
 
+impl UnnamedTag {
+    /// Look up a member of a `TAG_Compound` by name. Returns `None` if `self` is not a
+    /// `Compound`, or if no member has that name.
+    ///
+    /// If a name appears more than once (which the format doesn't forbid), the *last* matching
+    /// entry wins, matching [`into_map`](#method.into_map).
+    pub fn get(&self, name: &str) -> Option<&UnnamedTag> {
+        match self {
+            UnnamedTag::Compound(members) => members
+                .iter()
+                .rev()
+                .find(|tag| tag.name == name)
+                .map(|tag| &tag.content),
+            _ => None,
+        }
+    }
+
+    /// Convert a `TAG_Compound` into an [`IndexMap`] keyed by member name, preserving insertion
+    /// order. If a name appears more than once, the *last* entry wins: folding the members left
+    /// to right and inserting as we go naturally yields this behavior, since `IndexMap` insertion
+    /// overwrites the value (but not the position) of an existing key.
+    ///
+    /// Returns an empty map if `self` is not a `Compound`.
+    pub fn into_map(self) -> IndexMap<String, UnnamedTag> {
+        match self {
+            UnnamedTag::Compound(members) => members
+                .into_iter()
+                .map(|tag| (tag.name, tag.content))
+                .collect(),
+            _ => IndexMap::new(),
+        }
+    }
+}
+
 fn name<I>() -> impl Parser<Input = I, Output = String>
 where
     I: Stream<Item = u8>,
@@ -72,7 +127,14 @@ where
 {
     be_u16()
         .then(|length| count(length as usize, any()))
-        .map(|contents: Vec<u8>| String::from_utf8(contents).unwrap())
+        .then(|contents: Vec<u8>| {
+            combine::parser(move |input| match mutf8_to_string(&contents) {
+                Ok(name) => combine::value(name).parse_stream(input),
+                Err(_) => unexpected("invalid Modified UTF-8 in tag name")
+                    .map(|()| String::new())
+                    .parse_stream(input),
+            })
+        })
 }
 
 fn end_tag<I>() -> impl Parser<Input = I, Output = NamedTag>
@@ -136,6 +198,28 @@ where
     name().map(UnnamedTag::String)
 }
 
+fn intarray_tag<I>() -> impl Parser<Input = I, Output = UnnamedTag>
+where
+    I: Stream<Item = u8>,
+    // Necessary due to rust-lang/rust#24159
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    be_i32()
+        .then(|length| count(length as usize, be_i32()))
+        .map(UnnamedTag::IntArray)
+}
+
+fn longarray_tag<I>() -> impl Parser<Input = I, Output = UnnamedTag>
+where
+    I: Stream<Item = u8>,
+    // Necessary due to rust-lang/rust#24159
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    be_i32()
+        .then(|length| count(length as usize, be_i64()))
+        .map(UnnamedTag::LongArray)
+}
+
 fn list_tag<I>() -> impl Parser<Input = I, Output = UnnamedTag>
 where
     I: Stream<Item = u8>,
@@ -160,6 +244,8 @@ where
                     8 => string_tag().parse_stream(input),
                     9 => list_tag().parse_stream(input),
                     10 => compound_tag().parse_stream(input),
+                    11 => intarray_tag().parse_stream(input),
+                    12 => longarray_tag().parse_stream(input),
                     _ => unexpected("Invalid tagId on TAG_List")
                         .map(|()| UnnamedTag::End)
                         .parse_stream(input),
@@ -214,7 +300,9 @@ where
         do_it!(7 => bytearray_tag()),
         do_it!(8 => string_tag()),
         do_it!(9 => list_tag()),
-        do_it!(10 => compound_tag())
+        do_it!(10 => compound_tag()),
+        do_it!(11 => intarray_tag()),
+        do_it!(12 => longarray_tag())
     )
 }
 
@@ -230,3 +318,138 @@ pub fn decode_uncompressed<R: Read>(input: R) -> Result<NamedTag, failure::Error
     let mut stream = BufferedStream::new(State::new(ReadStream::new(input)), 4096);
     Ok(named_tag().parse_stream(&mut stream).map_err(|c| c.into_inner().error)?.0)
 }
+
+/// Decode a [`Read`] instance, automatically detecting whether its contents are gzipped,
+/// zlib-compressed, or raw uncompressed NBT.
+///
+/// This peeks at the first two bytes of the stream to tell the formats apart (`0x1F 0x8B` for
+/// gzip, a zlib header such as `0x78 ..` otherwise), then hands the *whole*, unconsumed stream
+/// off to the matching decoder, so detection is non-destructive.
+pub fn decode_auto<R: Read>(mut input: R) -> Result<NamedTag, failure::Error> {
+    let mut header = [0u8; 2];
+    let read = read_fully(&mut input, &mut header)?;
+    let input = io::Cursor::new(header[..read].to_vec()).chain(input);
+
+    if header[..read] == [0x1F, 0x8B] {
+        decode(input)
+    } else if read == 2 && header[0] & 0x0F == 8 && (u16::from(header[0]) * 256 + u16::from(header[1])) % 31 == 0 {
+        let decoder = libflate::zlib::Decoder::new(input)?;
+        decode_uncompressed(decoder)
+    } else {
+        decode_uncompressed(input)
+    }
+}
+
+/// Read from `input` until `buf` is filled or the stream is exhausted, returning the number of
+/// bytes actually read.
+fn read_fully<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<usize, io::Error> {
+    let mut read = 0;
+    while read < buf.len() {
+        match input.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::{decode_auto, decode_uncompressed, NamedTag, UnnamedTag};
+
+    #[test]
+    fn duplicate_keys_resolve_to_the_last_entry() {
+        let compound = UnnamedTag::Compound(vec![
+            NamedTag {
+                name: "x".to_owned(),
+                content: UnnamedTag::Int(1),
+            },
+            NamedTag {
+                name: "x".to_owned(),
+                content: UnnamedTag::Int(2),
+            },
+        ]);
+
+        assert_eq!(compound.get("x"), Some(&UnnamedTag::Int(2)));
+        assert_eq!(
+            compound.into_map().get("x"),
+            Some(&UnnamedTag::Int(2))
+        );
+    }
+
+    // A minimal valid payload: an empty `TAG_Compound` named "".
+    fn empty_compound_bytes() -> Vec<u8> {
+        vec![10, 0, 0, 0]
+    }
+
+    fn empty_compound() -> NamedTag {
+        NamedTag {
+            name: String::new(),
+            content: UnnamedTag::Compound(vec![]),
+        }
+    }
+
+    #[test]
+    fn decode_auto_detects_raw() {
+        let decoded = decode_auto(Cursor::new(empty_compound_bytes())).unwrap();
+        assert_eq!(decoded, empty_compound());
+    }
+
+    #[test]
+    fn decode_auto_detects_gzip() {
+        let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&empty_compound_bytes()).unwrap();
+        let compressed = encoder.finish().into_result().unwrap();
+
+        let decoded = decode_auto(Cursor::new(compressed)).unwrap();
+        assert_eq!(decoded, empty_compound());
+    }
+
+    #[test]
+    fn decode_auto_detects_zlib() {
+        let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&empty_compound_bytes()).unwrap();
+        let compressed = encoder.finish().into_result().unwrap();
+
+        let decoded = decode_auto(Cursor::new(compressed)).unwrap();
+        assert_eq!(decoded, empty_compound());
+    }
+
+    #[test]
+    fn decodes_int_array_and_long_array() {
+        let mut bytes = vec![10, 0, 0]; // TAG_Compound named ""
+
+        // TAG_Int_Array named "ia": [1, 2]
+        bytes.extend(&[11, 0, 2, b'i', b'a']);
+        bytes.extend(&(2i32).to_be_bytes());
+        bytes.extend(&(1i32).to_be_bytes());
+        bytes.extend(&(2i32).to_be_bytes());
+
+        // TAG_Long_Array named "la": [3]
+        bytes.extend(&[12, 0, 2, b'l', b'a']);
+        bytes.extend(&(1i32).to_be_bytes());
+        bytes.extend(&(3i64).to_be_bytes());
+
+        bytes.push(0); // TAG_End
+
+        let decoded = decode_uncompressed(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            decoded,
+            NamedTag {
+                name: String::new(),
+                content: UnnamedTag::Compound(vec![
+                    NamedTag {
+                        name: "ia".to_owned(),
+                        content: UnnamedTag::IntArray(vec![1, 2]),
+                    },
+                    NamedTag {
+                        name: "la".to_owned(),
+                        content: UnnamedTag::LongArray(vec![3]),
+                    },
+                ]),
+            }
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Decoding of Java's Modified UTF-8 (MUTF-8/CESU-8), which is what NBT actually uses for its
+//! string payloads instead of standard UTF-8.
+
+use failure::Fail;
+
+/// An error produced when a byte buffer is not valid Modified UTF-8.
+#[allow(non_local_definitions)]
+#[derive(Debug, Fail)]
+#[fail(display = "invalid Modified UTF-8 at byte offset {}", offset)]
+pub struct Mutf8Error {
+    offset: usize,
+}
+
+/// Decode a Modified UTF-8 byte buffer, as used by NBT's `TAG_String` payloads, into a `String`.
+///
+/// Unlike standard UTF-8, `0xC0 0x80` encodes U+0000 and supplementary characters (outside the
+/// BMP) are encoded as a surrogate pair of two 3-byte sequences rather than a single 4-byte one.
+pub fn mutf8_to_string(bytes: &[u8]) -> Result<String, Mutf8Error> {
+    let mut out = String::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let offset = i;
+        let b0 = bytes[i];
+
+        if b0 & 0x80 == 0 {
+            out.push(b0 as char);
+            i += 1;
+        } else if b0 & 0xE0 == 0xC0 {
+            let b1 = *bytes.get(i + 1).ok_or(Mutf8Error { offset })?;
+            if b1 & 0xC0 != 0x80 {
+                return Err(Mutf8Error { offset });
+            }
+            let code_point = ((u32::from(b0) & 0x1F) << 6) | (u32::from(b1) & 0x3F);
+            out.push(char::from_u32(code_point).ok_or(Mutf8Error { offset })?);
+            i += 2;
+        } else if b0 & 0xF0 == 0xE0 {
+            let (code_point, consumed) = decode_three_byte(bytes, i).ok_or(Mutf8Error { offset })?;
+
+            if (0xD800..=0xDBFF).contains(&code_point) {
+                let (low, low_consumed) =
+                    decode_three_byte(bytes, i + consumed).ok_or(Mutf8Error { offset })?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(Mutf8Error { offset });
+                }
+                let combined = 0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+                out.push(char::from_u32(combined).ok_or(Mutf8Error { offset })?);
+                i += consumed + low_consumed;
+            } else {
+                out.push(char::from_u32(code_point).ok_or(Mutf8Error { offset })?);
+                i += consumed;
+            }
+        } else {
+            return Err(Mutf8Error { offset });
+        }
+    }
+
+    Ok(out)
+}
+
+/// Encode a `str` as Modified UTF-8, the inverse of [`mutf8_to_string`]: embedded NULs become
+/// `0xC0 0x80` and supplementary characters are split into a surrogate pair of two 3-byte
+/// sequences instead of a single 4-byte one.
+pub fn string_to_mutf8(s: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len());
+
+    for c in s.chars() {
+        let code_point = c as u32;
+
+        if code_point == 0 {
+            out.extend_from_slice(&[0xC0, 0x80]);
+        } else if code_point <= 0x7F {
+            out.push(code_point as u8);
+        } else if code_point <= 0x7FF {
+            out.push(0xC0 | (code_point >> 6) as u8);
+            out.push(0x80 | (code_point & 0x3F) as u8);
+        } else if code_point <= 0xFFFF {
+            push_three_byte(&mut out, code_point);
+        } else {
+            let adjusted = code_point - 0x10000;
+            let high_surrogate = 0xD800 + (adjusted >> 10);
+            let low_surrogate = 0xDC00 + (adjusted & 0x3FF);
+            push_three_byte(&mut out, high_surrogate);
+            push_three_byte(&mut out, low_surrogate);
+        }
+    }
+
+    out
+}
+
+/// Append a single 16-bit code point as a 3-byte MUTF-8 sequence.
+fn push_three_byte(out: &mut Vec<u8>, code_point: u32) {
+    out.push(0xE0 | (code_point >> 12) as u8);
+    out.push(0x80 | ((code_point >> 6) & 0x3F) as u8);
+    out.push(0x80 | (code_point & 0x3F) as u8);
+}
+
+/// Decode a single 3-byte MUTF-8 sequence starting at `bytes[i]`, returning its 16-bit code
+/// point and the number of bytes consumed (always 3).
+fn decode_three_byte(bytes: &[u8], i: usize) -> Option<(u32, usize)> {
+    let b0 = *bytes.get(i)?;
+    let b1 = *bytes.get(i + 1)?;
+    let b2 = *bytes.get(i + 2)?;
+
+    if b0 & 0xF0 != 0xE0 || b1 & 0xC0 != 0x80 || b2 & 0xC0 != 0x80 {
+        return None;
+    }
+
+    let code_point =
+        ((u32::from(b0) & 0x0F) << 12) | ((u32::from(b1) & 0x3F) << 6) | (u32::from(b2) & 0x3F);
+    Some((code_point, 3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mutf8_to_string, string_to_mutf8};
+
+    #[test]
+    fn ascii_round_trips() {
+        let bytes = string_to_mutf8("Hello, world!");
+        assert_eq!(bytes, b"Hello, world!");
+        assert_eq!(mutf8_to_string(&bytes).unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn embedded_nul_round_trips() {
+        let bytes = string_to_mutf8("a\0b");
+        assert_eq!(bytes, [b'a', 0xC0, 0x80, b'b']);
+        assert_eq!(mutf8_to_string(&bytes).unwrap(), "a\0b");
+    }
+
+    #[test]
+    fn supplementary_plane_char_round_trips() {
+        let bytes = string_to_mutf8("\u{1F600}");
+        // Encoded as a surrogate pair: two 3-byte sequences, not one 4-byte sequence.
+        assert_eq!(bytes.len(), 6);
+        assert_eq!(mutf8_to_string(&bytes).unwrap(), "\u{1F600}");
+    }
+
+    #[test]
+    fn truncated_multi_byte_sequence_errors() {
+        assert!(mutf8_to_string(&[0xC2]).is_err());
+        assert!(mutf8_to_string(&[0xE0, 0x80]).is_err());
+    }
+
+    #[test]
+    fn lone_high_surrogate_errors() {
+        // 0xD800 encoded as a 3-byte sequence with no following low surrogate.
+        assert!(mutf8_to_string(&[0xED, 0xA0, 0x80]).is_err());
+    }
+
+    #[test]
+    fn lone_low_surrogate_errors() {
+        // 0xDC00 encoded as a 3-byte sequence with no preceding high surrogate.
+        assert!(mutf8_to_string(&[0xED, 0xB0, 0x80]).is_err());
+    }
+
+    #[test]
+    fn bad_continuation_byte_errors() {
+        assert!(mutf8_to_string(&[0xC2, b'A']).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! A pull-style, event-based parser inspired by fastnbt's `stream` module. Unlike [`decode`],
+//! it never materializes a full [`NamedTag`] tree, which makes it far cheaper for callers who
+//! only care about a handful of fields in a large world file and want to skip the rest.
+//!
+//! [`decode`]: ../fn.decode.html
+//! [`NamedTag`]: ../struct.NamedTag.html
+
+use std::io::Read;
+
+use mutf8::mutf8_to_string;
+
+/// The id byte that precedes every tag in the binary format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagId {
+    End = 0,
+    Byte = 1,
+    Short = 2,
+    Int = 3,
+    Long = 4,
+    Float = 5,
+    Double = 6,
+    ByteArray = 7,
+    String = 8,
+    List = 9,
+    Compound = 10,
+    IntArray = 11,
+    LongArray = 12,
+}
+
+impl TagId {
+    fn from_u8(id: u8) -> Result<TagId, failure::Error> {
+        Ok(match id {
+            0 => TagId::End,
+            1 => TagId::Byte,
+            2 => TagId::Short,
+            3 => TagId::Int,
+            4 => TagId::Long,
+            5 => TagId::Float,
+            6 => TagId::Double,
+            7 => TagId::ByteArray,
+            8 => TagId::String,
+            9 => TagId::List,
+            10 => TagId::Compound,
+            11 => TagId::IntArray,
+            12 => TagId::LongArray,
+            _ => bail!("invalid tag id {}", id),
+        })
+    }
+}
+
+/// A single shallow parsing event.
+///
+/// The name is `Some` for compound members and `None` for list elements, since list entries have
+/// no names in the binary format. `ListEnd` and `CompoundEnd` carry no name of their own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Byte(Option<String>, i8),
+    Short(Option<String>, i16),
+    Int(Option<String>, i32),
+    Long(Option<String>, i64),
+    Float(Option<String>, f32),
+    Double(Option<String>, f64),
+    ByteArray(Option<String>, Vec<i8>),
+    String(Option<String>, String),
+    IntArray(Option<String>, Vec<i32>),
+    LongArray(Option<String>, Vec<i64>),
+    ListStart(Option<String>, TagId, i32),
+    ListEnd,
+    CompoundStart(Option<String>),
+    CompoundEnd,
+}
+
+/// What we're currently reading members of: a compound (terminated by `TAG_End`) or a list
+/// (terminated by an exhausted element count).
+enum Frame {
+    Compound,
+    List { remaining: i32, tag_id: TagId },
+}
+
+/// A pull parser that yields [`Event`]s from a [`Read`] source one at a time, without any prior
+/// knowledge of the tree's structure.
+pub struct Parser<R> {
+    input: R,
+    stack: Vec<Frame>,
+    finished: bool,
+}
+
+impl<R: Read> Parser<R> {
+    /// Create a new parser reading from `input`.
+    pub fn new(input: R) -> Self {
+        Parser {
+            input,
+            stack: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Read the next event, or `None` once the top-level tag has been fully consumed.
+    pub fn next_event(&mut self) -> Result<Option<Event>, failure::Error> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let at_top_level = self.stack.is_empty();
+
+        match self.stack.last() {
+            Some(&Frame::List { remaining, tag_id }) => {
+                if remaining == 0 {
+                    self.stack.pop();
+                    self.finish_if_empty();
+                    return Ok(Some(Event::ListEnd));
+                }
+
+                if let Some(&mut Frame::List { ref mut remaining, .. }) = self.stack.last_mut() {
+                    *remaining -= 1;
+                }
+                self.read_payload(None, tag_id).map(Some)
+            }
+            Some(&Frame::Compound) | None => {
+                let tag_id = TagId::from_u8(read_u8(&mut self.input)?)?;
+
+                if tag_id == TagId::End {
+                    return Ok(if self.stack.pop().is_some() {
+                        self.finish_if_empty();
+                        Some(Event::CompoundEnd)
+                    } else {
+                        self.finished = true;
+                        None
+                    });
+                }
+
+                let name = Some(read_name(&mut self.input)?);
+                let event = self.read_payload(name, tag_id)?;
+
+                // A scalar root (e.g. a bare `TAG_Int`) never pushes a `Frame`, so unlike
+                // `Compound`/`List` roots it wouldn't otherwise ever mark us as finished.
+                if at_top_level {
+                    self.finish_if_empty();
+                }
+
+                Ok(Some(event))
+            }
+        }
+    }
+
+    fn finish_if_empty(&mut self) {
+        if self.stack.is_empty() {
+            self.finished = true;
+        }
+    }
+
+    fn read_payload(&mut self, name: Option<String>, tag_id: TagId) -> Result<Event, failure::Error> {
+        Ok(match tag_id {
+            TagId::End => bail!("TAG_End cannot be the element type of a non-empty list"),
+            TagId::Byte => Event::Byte(name, read_i8(&mut self.input)?),
+            TagId::Short => Event::Short(name, read_i16(&mut self.input)?),
+            TagId::Int => Event::Int(name, read_i32(&mut self.input)?),
+            TagId::Long => Event::Long(name, read_i64(&mut self.input)?),
+            TagId::Float => Event::Float(name, read_f32(&mut self.input)?),
+            TagId::Double => Event::Double(name, read_f64(&mut self.input)?),
+            TagId::ByteArray => Event::ByteArray(name, read_i8_array(&mut self.input)?),
+            TagId::String => Event::String(name, read_name(&mut self.input)?),
+            TagId::IntArray => Event::IntArray(name, read_i32_array(&mut self.input)?),
+            TagId::LongArray => Event::LongArray(name, read_i64_array(&mut self.input)?),
+            TagId::List => {
+                let element_id = TagId::from_u8(read_u8(&mut self.input)?)?;
+                let length = read_i32(&mut self.input)?;
+                self.stack.push(Frame::List {
+                    remaining: length,
+                    tag_id: element_id,
+                });
+                Event::ListStart(name, element_id, length)
+            }
+            TagId::Compound => {
+                self.stack.push(Frame::Compound);
+                Event::CompoundStart(name)
+            }
+        })
+    }
+}
+
+fn read_u8<R: Read>(input: &mut R) -> Result<u8, failure::Error> {
+    let mut buf = [0u8; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_i8<R: Read>(input: &mut R) -> Result<i8, failure::Error> {
+    Ok(read_u8(input)? as i8)
+}
+
+fn read_u16<R: Read>(input: &mut R) -> Result<u16, failure::Error> {
+    let mut buf = [0u8; 2];
+    input.read_exact(&mut buf)?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+fn read_i16<R: Read>(input: &mut R) -> Result<i16, failure::Error> {
+    let mut buf = [0u8; 2];
+    input.read_exact(&mut buf)?;
+    Ok(i16::from_be_bytes(buf))
+}
+
+fn read_i32<R: Read>(input: &mut R) -> Result<i32, failure::Error> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(i32::from_be_bytes(buf))
+}
+
+fn read_i64<R: Read>(input: &mut R) -> Result<i64, failure::Error> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(i64::from_be_bytes(buf))
+}
+
+fn read_f32<R: Read>(input: &mut R) -> Result<f32, failure::Error> {
+    Ok(f32::from_bits(read_i32(input)? as u32))
+}
+
+fn read_f64<R: Read>(input: &mut R) -> Result<f64, failure::Error> {
+    Ok(f64::from_bits(read_i64(input)? as u64))
+}
+
+fn read_name<R: Read>(input: &mut R) -> Result<String, failure::Error> {
+    let length = read_u16(input)?;
+    let mut buf = vec![0u8; length as usize];
+    input.read_exact(&mut buf)?;
+    Ok(mutf8_to_string(&buf)?)
+}
+
+fn read_i8_array<R: Read>(input: &mut R) -> Result<Vec<i8>, failure::Error> {
+    let length = read_i32(input)?;
+    (0..length).map(|_| read_i8(input)).collect()
+}
+
+fn read_i32_array<R: Read>(input: &mut R) -> Result<Vec<i32>, failure::Error> {
+    let length = read_i32(input)?;
+    (0..length).map(|_| read_i32(input)).collect()
+}
+
+fn read_i64_array<R: Read>(input: &mut R) -> Result<Vec<i64>, failure::Error> {
+    let length = read_i32(input)?;
+    (0..length).map(|_| read_i64(input)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Event, Parser, TagId};
+
+    #[test]
+    fn malformed_list_with_tag_end_element_errors_instead_of_panicking() {
+        // TAG_List named "", element tag id TAG_End (0), count 1.
+        let bytes = [9, 0, 0, 0, 0, 0, 0, 1];
+        let mut parser = Parser::new(Cursor::new(bytes));
+
+        assert_eq!(
+            parser.next_event().unwrap(),
+            Some(Event::ListStart(Some(String::new()), TagId::End, 1))
+        );
+        assert!(parser.next_event().is_err());
+    }
+
+    #[test]
+    fn scalar_root_terminates_after_one_event() {
+        // TAG_Int named "", value 42.
+        let bytes = [3, 0, 0, 0, 0, 0, 42];
+        let mut parser = Parser::new(Cursor::new(bytes));
+
+        assert_eq!(
+            parser.next_event().unwrap(),
+            Some(Event::Int(Some(String::new()), 42))
+        );
+        assert_eq!(parser.next_event().unwrap(), None);
+    }
+}